@@ -0,0 +1,331 @@
+//! Background thread that drives [`SerialTty`] I/O and feeds the terminal.
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use mio::{Events, Interest, Poll, Token};
+
+use super::capture::{CaptureWriter, Direction};
+use super::{ClearBuffer, ControlLines, NewlineMode, SerialTty, SerialTtyOptions};
+
+const SERIAL_TOKEN: Token = Token(0);
+const CHANNEL_TOKEN: Token = Token(1);
+
+/// Messages accepted by a running [`EventLoop`].
+pub enum Msg {
+    /// Bytes to write out to the serial device.
+    Input(Cow<'static, [u8]>),
+    /// Re-apply baud rate / framing / flow control to the open port, e.g.
+    /// from a UI baud-rate dropdown, without reopening the device.
+    Reconfigure(SerialTtyOptions),
+    /// Drive the DTR output line.
+    SetDtr(bool),
+    /// Drive the RTS output line.
+    SetRts(bool),
+    /// Assert a transmit BREAK condition for the given duration.
+    SendBreak(Duration),
+    /// Read back the CTS/DSR/DCD/RI input lines, replying on the given
+    /// channel since the caller no longer holds the open port directly.
+    ReadControlLines(mpsc::Sender<io::Result<ControlLines>>),
+    /// Stop the loop, letting the background thread exit; the thread is
+    /// joined through the handle returned by [`EventLoop::spawn`].
+    Shutdown,
+}
+
+/// Handle used to send [`Msg`]s into a running [`EventLoop`].
+#[derive(Clone)]
+pub struct Notifier(pub mio::Waker, pub mpsc::Sender<Msg>);
+
+impl Notifier {
+    pub fn notify<B: Into<Cow<'static, [u8]>>>(&self, bytes: B) {
+        let bytes = bytes.into();
+        if !bytes.is_empty() && self.1.send(Msg::Input(bytes)).is_ok() {
+            let _ = self.0.wake();
+        }
+    }
+
+    /// Ask the running loop to retune the port to `config`, e.g. in response
+    /// to a UI baud-rate change.
+    pub fn request_reconfigure(&self, config: SerialTtyOptions) {
+        if self.1.send(Msg::Reconfigure(config)).is_ok() {
+            let _ = self.0.wake();
+        }
+    }
+
+    /// Drive the DTR output line on the port owned by the running loop.
+    pub fn set_dtr(&self, on: bool) {
+        if self.1.send(Msg::SetDtr(on)).is_ok() {
+            let _ = self.0.wake();
+        }
+    }
+
+    /// Drive the RTS output line on the port owned by the running loop.
+    pub fn set_rts(&self, on: bool) {
+        if self.1.send(Msg::SetRts(on)).is_ok() {
+            let _ = self.0.wake();
+        }
+    }
+
+    /// Assert a transmit BREAK condition for `duration` on the port owned by
+    /// the running loop.
+    pub fn send_break(&self, duration: Duration) {
+        if self.1.send(Msg::SendBreak(duration)).is_ok() {
+            let _ = self.0.wake();
+        }
+    }
+
+    /// Read back the CTS/DSR/DCD/RI input lines from the port owned by the
+    /// running loop, blocking until the loop replies.
+    pub fn read_control_lines(&self) -> io::Result<ControlLines> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.1.send(Msg::ReadControlLines(reply_tx)).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "serial event loop has shut down")
+        })?;
+        let _ = self.0.wake();
+
+        reply_rx.recv().map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "serial event loop has shut down")
+        })?
+    }
+}
+
+/// Drives a [`SerialTty`] on a dedicated thread, decoupling serial I/O from
+/// the UI thread.
+pub struct EventLoop {
+    tty: SerialTty,
+    rx: mpsc::Receiver<Msg>,
+    poll: Poll,
+    capture: Option<CaptureWriter>,
+    local_echo: bool,
+    newline_mode: NewlineMode,
+}
+
+impl EventLoop {
+    pub fn new(mut tty: SerialTty, config: &SerialTtyOptions) -> io::Result<(Self, Notifier)> {
+        // Stale bytes queued at the wrong baud rate or left over from a
+        // previous session would otherwise be misinterpreted as terminal
+        // output, so start from a clean input buffer.
+        let _ = tty.clear(ClearBuffer::Input);
+
+        let poll = Poll::new()?;
+        let waker = mio::Waker::new(poll.registry(), CHANNEL_TOKEN)?;
+        poll.registry().register(
+            &mut *tty,
+            SERIAL_TOKEN,
+            Interest::READABLE | Interest::WRITABLE,
+        )?;
+
+        let capture = match &config.capture_path {
+            Some(path) => Some(CaptureWriter::create(path)?),
+            None => None,
+        };
+
+        let (tx, rx) = mpsc::channel();
+        Ok((
+            Self {
+                tty,
+                rx,
+                poll,
+                capture,
+                local_echo: config.local_echo,
+                newline_mode: config.newline_mode,
+            },
+            Notifier(waker, tx),
+        ))
+    }
+
+    /// Spawn the loop onto a background thread and return a join handle.
+    pub fn spawn(mut self, mut on_data: impl FnMut(&[u8]) + Send + 'static) -> JoinHandle<()>
+    where
+        Self: Send,
+    {
+        std::thread::spawn(move || {
+            let mut events = Events::with_capacity(1024);
+            let mut read_buf = [0u8; 4096];
+            let mut write_queue: std::collections::VecDeque<u8> =
+                std::collections::VecDeque::new();
+
+            'event_loop: loop {
+                if self.poll.poll(&mut events, None).is_err() {
+                    break;
+                }
+
+                for event in events.iter() {
+                    match event.token() {
+                        CHANNEL_TOKEN => {
+                            while let Ok(msg) = self.rx.try_recv() {
+                                match msg {
+                                    Msg::Input(bytes) => {
+                                        let bytes =
+                                            translate_newline(&bytes, self.newline_mode);
+
+                                        if let Some(capture) = &mut self.capture {
+                                            let _ = capture.record(Direction::Tx, &bytes);
+                                        }
+                                        if self.local_echo {
+                                            on_data(&bytes);
+                                        }
+
+                                        write_queue.extend(bytes.iter());
+
+                                        // `Interest::WRITABLE` is edge-triggered
+                                        // and fires once right after
+                                        // registration; without writing here,
+                                        // newly queued bytes could wait
+                                        // forever for an edge that never
+                                        // re-arms.
+                                        if !Self::drain_write_queue(
+                                            &mut self.tty,
+                                            &mut write_queue,
+                                        ) {
+                                            break 'event_loop;
+                                        }
+                                    },
+                                    Msg::Reconfigure(config) => {
+                                        if let Err(err) = self.tty.reconfigure(&config) {
+                                            log::warn!(
+                                                "failed to reconfigure serial port: {err}"
+                                            );
+                                        }
+                                    },
+                                    Msg::SetDtr(on) => {
+                                        if let Err(err) = self.tty.set_dtr(on) {
+                                            log::warn!("failed to set DTR: {err}");
+                                        }
+                                    },
+                                    Msg::SetRts(on) => {
+                                        if let Err(err) = self.tty.set_rts(on) {
+                                            log::warn!("failed to set RTS: {err}");
+                                        }
+                                    },
+                                    Msg::SendBreak(duration) => {
+                                        // Runs on its own thread so a long
+                                        // `duration` doesn't stall reads,
+                                        // writes or other queued messages.
+                                        self.tty.send_break_async(duration);
+                                    },
+                                    Msg::ReadControlLines(reply) => {
+                                        let _ = reply.send(self.tty.read_control_lines());
+                                    },
+                                    Msg::Shutdown => break 'event_loop,
+                                }
+                            }
+                        },
+                        SERIAL_TOKEN => {
+                            if event.is_readable() {
+                                match self.tty.read(&mut read_buf) {
+                                    Ok(0) => break 'event_loop,
+                                    Ok(n) => {
+                                        if let Some(capture) = &mut self.capture {
+                                            let _ =
+                                                capture.record(Direction::Rx, &read_buf[..n]);
+                                        }
+                                        on_data(&read_buf[..n]);
+                                    },
+                                    // Expected on a non-blocking fd when there's
+                                    // nothing to read yet; only a real I/O
+                                    // error means the device went away.
+                                    Err(err)
+                                        if err.kind() == io::ErrorKind::WouldBlock
+                                            || err.kind() == io::ErrorKind::Interrupted => {},
+                                    Err(_) => break 'event_loop,
+                                }
+                            }
+
+                            if event.is_writable()
+                                && !Self::drain_write_queue(&mut self.tty, &mut write_queue)
+                            {
+                                break 'event_loop;
+                            }
+                        },
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        })
+    }
+
+    /// Write as much of `write_queue` as the device will currently accept.
+    /// Returns `false` on a fatal I/O error, in which case the caller should
+    /// stop the loop; a full kernel TX buffer (`WouldBlock`) just leaves the
+    /// remainder queued for the next writable event.
+    fn drain_write_queue(
+        tty: &mut SerialTty,
+        write_queue: &mut std::collections::VecDeque<u8>,
+    ) -> bool {
+        if write_queue.is_empty() {
+            return true;
+        }
+
+        let pending = write_queue.make_contiguous();
+        match tty.write(pending) {
+            Ok(n) => {
+                write_queue.drain(..n);
+                true
+            },
+            Err(err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::Interrupted =>
+            {
+                true
+            },
+            Err(_) => false,
+        }
+    }
+}
+
+/// Translate outgoing carriage returns (the Enter key) to the sequence
+/// configured by [`NewlineMode`], leaving every other byte untouched.
+fn translate_newline(bytes: &[u8], mode: NewlineMode) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte != b'\r' {
+            out.push(byte);
+            continue;
+        }
+
+        match mode {
+            NewlineMode::Lf => out.push(b'\n'),
+            NewlineMode::CrLf => out.extend_from_slice(b"\r\n"),
+            NewlineMode::Cr => out.push(b'\r'),
+        }
+    }
+    out
+}
+
+/// Replay a capture file back into the terminal on a background thread, at
+/// either the original inter-byte timing or as fast as possible.
+pub fn replay(
+    path: std::path::PathBuf,
+    realtime: bool,
+    mut on_data: impl FnMut(&[u8]) + Send + 'static,
+) -> JoinHandle<io::Result<()>> {
+    std::thread::spawn(move || super::capture::replay(&path, realtime, |bytes| on_data(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translate_newline_lf() {
+        assert_eq!(translate_newline(b"ls\r", NewlineMode::Lf), b"ls\n");
+    }
+
+    #[test]
+    fn translate_newline_crlf() {
+        assert_eq!(translate_newline(b"ls\r", NewlineMode::CrLf), b"ls\r\n");
+    }
+
+    #[test]
+    fn translate_newline_cr() {
+        assert_eq!(translate_newline(b"ls\r", NewlineMode::Cr), b"ls\r");
+    }
+
+    #[test]
+    fn translate_newline_leaves_other_bytes_untouched() {
+        assert_eq!(translate_newline(b"abc\x1b[A", NewlineMode::Lf), b"abc\x1b[A");
+    }
+}
@@ -32,6 +32,7 @@ const DEFAULT_BAUDRATE: u32 = 115200;
 #[cfg(any(target_os = "macos", all(test, target_os = "macos")))]
 mod prolific_apple_patch;
 
+pub mod capture;
 pub mod event_loop;
 
 #[cfg(unix)]
@@ -40,6 +41,63 @@ pub(crate) mod unix;
 #[cfg(windows)]
 pub(crate) mod windows;
 
+/// USB identity used to resolve the actual `port_name` at open time, so an
+/// app can reconnect to "the same adapter" even after the OS renumbers the
+/// device node across reboots/hotplug.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct UsbMatch {
+    pub vid: u16,
+    pub pid: u16,
+    pub serial: Option<String>,
+}
+
+impl UsbMatch {
+    fn matches(&self, info: &mio_serial::UsbPortInfo) -> bool {
+        info.vid == self.vid
+            && info.pid == self.pid
+            && match &self.serial {
+                Some(serial) => info.serial_number.as_deref() == Some(serial.as_str()),
+                None => true,
+            }
+    }
+}
+
+/// A serial port known to the OS, with USB identity exposed so callers can
+/// match on VID/PID/serial-number instead of the unstable `port_name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialPortInfo {
+    pub port_name: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+}
+
+/// List the serial ports currently visible to the OS, including USB
+/// VID/PID/serial-number/manufacturer metadata where available.
+pub fn enumerate() -> Vec<SerialPortInfo> {
+    mio_serial::available_ports()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|port| match port.port_type {
+            mio_serial::SerialPortType::UsbPort(usb) => SerialPortInfo {
+                port_name: port.port_name,
+                vid: Some(usb.vid),
+                pid: Some(usb.pid),
+                serial_number: usb.serial_number,
+                manufacturer: usb.manufacturer,
+            },
+            _ => SerialPortInfo {
+                port_name: port.port_name,
+                vid: None,
+                pid: None,
+                serial_number: None,
+                manufacturer: None,
+            },
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct SerialTtyOptions {
     pub name: String,
@@ -50,6 +108,28 @@ pub struct SerialTtyOptions {
     pub stop_bits: mio_serial::StopBits,
     pub timeout: std::time::Duration,
     pub dtr_on_open: Option<bool>,
+    pub exclusive: bool,
+    /// When set, every RX/TX byte is logged to this file for later replay.
+    pub capture_path: Option<std::path::PathBuf>,
+    /// When set, `new()` resolves `name` by USB identity instead of using it
+    /// as a fixed device path; if no port matches, `name` is used as is.
+    pub match_usb: Option<UsbMatch>,
+    /// Echo typed keystrokes into the terminal grid locally, for devices
+    /// that don't echo on their own.
+    pub local_echo: bool,
+    /// Sequence outgoing Enter keystrokes are translated to.
+    pub newline_mode: NewlineMode,
+}
+
+/// Outgoing Enter-key translation applied by [`event_loop`].
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub enum NewlineMode {
+    /// Translate to a bare line feed (`\n`).
+    Lf,
+    /// Translate to carriage-return + line-feed (`\r\n`).
+    CrLf,
+    /// Translate to a bare carriage return (`\r`).
+    Cr,
 }
 
 impl Default for SerialTtyOptions {
@@ -63,6 +143,11 @@ impl Default for SerialTtyOptions {
             stop_bits: mio_serial::StopBits::One,
             timeout: std::time::Duration::from_millis(0),
             dtr_on_open: Some(true),
+            exclusive: false,
+            capture_path: None,
+            match_usb: None,
+            local_echo: false,
+            newline_mode: NewlineMode::Cr,
         }
     }
 }
@@ -126,6 +211,46 @@ impl SerialTtyOptions {
         self.timeout = timeout;
         self
     }
+
+    /// Request exclusive access to the device, so `open()` fails instead of
+    /// silently sharing the port with another process.
+    #[must_use]
+    pub fn set_exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Enable session capture, logging every RX/TX byte to `path` for later
+    /// replay via [`capture::replay`].
+    #[must_use]
+    pub fn set_capture_path(
+        mut self,
+        path: impl Into<std::path::PathBuf>,
+    ) -> Self {
+        self.capture_path = Some(path.into());
+        self
+    }
+
+    /// Resolve `name` by USB identity at open time rather than a fixed
+    /// device path, so the same physical adapter is found even if the OS
+    /// renumbers it.
+    #[must_use]
+    pub fn set_match_usb(mut self, match_usb: UsbMatch) -> Self {
+        self.match_usb = Some(match_usb);
+        self
+    }
+
+    #[must_use]
+    pub fn set_local_echo(mut self, local_echo: bool) -> Self {
+        self.local_echo = local_echo;
+        self
+    }
+
+    #[must_use]
+    pub fn set_newline_mode(mut self, newline_mode: NewlineMode) -> Self {
+        self.newline_mode = newline_mode;
+        self
+    }
 }
 
 impl From<&SerialTtyOptions> for mio_serial::SerialPortBuilder {
@@ -134,9 +259,34 @@ impl From<&SerialTtyOptions> for mio_serial::SerialPortBuilder {
     }
 }
 
+/// Selects which queued buffer(s) a [`SerialTty::clear`] call discards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearBuffer {
+    /// Discard unread bytes received from the device.
+    Input,
+    /// Discard queued bytes not yet transmitted to the device.
+    Output,
+    /// Discard both the input and output buffers.
+    All,
+}
+
+/// Modem control-line states as read back from an open [`SerialTty`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ControlLines {
+    /// Clear To Send.
+    pub cts: bool,
+    /// Data Set Ready.
+    pub dsr: bool,
+    /// Data Carrier Detect.
+    pub dcd: bool,
+    /// Ring Indicator.
+    pub ri: bool,
+}
+
 #[derive(Debug)]
 pub struct SerialTty {
     stream: mio_serial::SerialStream,
+    exclusive: bool,
 }
 
 impl Deref for SerialTty {
@@ -153,6 +303,89 @@ impl DerefMut for SerialTty {
     }
 }
 
+impl SerialTty {
+    /// Drive the DTR (Data Terminal Ready) output line.
+    ///
+    /// Many embedded devices wire DTR/RTS to a reset or bootloader-entry
+    /// line, so this needs to be toggleable after the port is already open.
+    pub fn set_dtr(&self, on: bool) -> Result<()> {
+        #[cfg(unix)]
+        return unix::set_dtr(&self.stream, on);
+        #[cfg(windows)]
+        return windows::set_dtr(&self.stream, on);
+    }
+
+    /// Drive the RTS (Request To Send) output line.
+    pub fn set_rts(&self, on: bool) -> Result<()> {
+        #[cfg(unix)]
+        return unix::set_rts(&self.stream, on);
+        #[cfg(windows)]
+        return windows::set_rts(&self.stream, on);
+    }
+
+    /// Read back the current state of the CTS/DSR/DCD/RI input lines.
+    pub fn read_control_lines(&self) -> Result<ControlLines> {
+        #[cfg(unix)]
+        return unix::read_control_lines(&self.stream);
+        #[cfg(windows)]
+        return windows::read_control_lines(&self.stream);
+    }
+
+    /// Discard queued, unprocessed bytes from the input and/or output
+    /// buffers. Useful for resyncing a protocol after garbage was received
+    /// at the wrong baud rate.
+    pub fn clear(&self, buffer_to_clear: ClearBuffer) -> Result<()> {
+        #[cfg(unix)]
+        return unix::clear(&self.stream, buffer_to_clear);
+        #[cfg(windows)]
+        return windows::clear(&self.stream, buffer_to_clear);
+    }
+
+    /// Apply a new baud rate / framing / flow control to the already-open
+    /// port, without dropping the fd (and with it any exclusive lock or
+    /// control-line state).
+    pub fn reconfigure(&self, config: &SerialTtyOptions) -> Result<()> {
+        #[cfg(unix)]
+        return unix::reconfigure(&self.stream, config);
+        #[cfg(windows)]
+        return windows::reconfigure(&self.stream, config);
+    }
+
+    /// Assert a transmit BREAK condition for `duration`, e.g. to interrupt a
+    /// Linux serial console into SysRq or a bootloader.
+    ///
+    /// This blocks the calling thread for `duration`; use
+    /// [`SerialTty::send_break_async`] from a thread that also has to keep
+    /// servicing I/O (e.g. the `event_loop`).
+    pub fn send_break(&self, duration: std::time::Duration) -> Result<()> {
+        #[cfg(unix)]
+        return unix::send_break(&self.stream, duration);
+        #[cfg(windows)]
+        return windows::send_break(&self.stream, duration);
+    }
+
+    /// Like [`SerialTty::send_break`], but runs the assert/sleep/clear cycle
+    /// on its own thread so a long `duration` can't stall a caller that also
+    /// has other I/O to service.
+    pub fn send_break_async(&self, duration: std::time::Duration) {
+        #[cfg(unix)]
+        let raw = std::os::unix::io::AsRawFd::as_raw_fd(&self.stream);
+        #[cfg(windows)]
+        let raw = std::os::windows::io::AsRawHandle::as_raw_handle(&self.stream);
+
+        std::thread::spawn(move || {
+            #[cfg(unix)]
+            let result = unix::send_break_raw(raw, duration);
+            #[cfg(windows)]
+            let result = windows::send_break_raw(raw, duration);
+
+            if let Err(err) = result {
+                log::warn!("failed to send break: {err}");
+            }
+        });
+    }
+}
+
 impl OnResize for SerialTty {
     /// Resize the PTY.
     ///
@@ -171,10 +404,17 @@ fn open(
     {
         let stream = mio_serial::SerialStream::open(&config.in_to_builder())?;
         unix::set_nonblocking_serial(&stream);
+        if config.exclusive {
+            unix::set_exclusive(&stream, true).map_err(|e| {
+                mio_serial::Error::new(mio_serial::ErrorKind::Io(e.kind()), e.to_string())
+            })?;
+        }
         Ok(stream)
     }
     #[cfg(windows)]
     {
+        // `CreateFile` is already opened without `FILE_SHARE_READ`/`_WRITE`,
+        // so Windows gives us exclusive access unconditionally.
         windows::open(config)
     }
 }
@@ -186,6 +426,29 @@ pub fn new(
     _window_id: u64,
 ) -> Result<SerialTty> {
     if let Ok(ports) = mio_serial::available_ports() {
+        let resolved_config;
+        let config = match &config.match_usb {
+            Some(usb_match) => {
+                let resolved_name = ports.iter().find_map(|p| match &p.port_type {
+                    mio_serial::SerialPortType::UsbPort(u)
+                        if usb_match.matches(u) =>
+                    {
+                        Some(p.port_name.clone())
+                    },
+                    _ => None,
+                });
+
+                match resolved_name {
+                    Some(name) => {
+                        resolved_config = config.clone().set_name(name);
+                        &resolved_config
+                    },
+                    None => config,
+                }
+            },
+            None => config,
+        };
+
         let stream = if let Some(matched) =
             ports.iter().find(|x| x.port_name == config.name)
         {
@@ -212,8 +475,53 @@ pub fn new(
             Err(Error::new(ErrorKind::InvalidData, "Unknown SerialTty Call"))?
         };
 
-        Ok(SerialTty { stream })
+        Ok(SerialTty { stream, exclusive: config.exclusive })
     } else {
         Err(Error::new(ErrorKind::InvalidData, "Unknown SerialTty Call"))
     }
 }
+
+#[cfg(unix)]
+impl Drop for SerialTty {
+    fn drop(&mut self) {
+        if self.exclusive {
+            let _ = unix::set_exclusive(&self.stream, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usb_port(vid: u16, pid: u16, serial_number: Option<&str>) -> mio_serial::UsbPortInfo {
+        mio_serial::UsbPortInfo {
+            vid,
+            pid,
+            serial_number: serial_number.map(str::to_owned),
+            manufacturer: None,
+            product: None,
+        }
+    }
+
+    #[test]
+    fn usb_match_requires_vid_and_pid() {
+        let m = UsbMatch { vid: 0x0403, pid: 0x6001, serial: None };
+        assert!(m.matches(&usb_port(0x0403, 0x6001, None)));
+        assert!(!m.matches(&usb_port(0x0403, 0x6002, None)));
+        assert!(!m.matches(&usb_port(0x0404, 0x6001, None)));
+    }
+
+    #[test]
+    fn usb_match_serial_number_is_optional_but_strict_when_set() {
+        let any_serial = UsbMatch { vid: 0x0403, pid: 0x6001, serial: None };
+        assert!(any_serial.matches(&usb_port(0x0403, 0x6001, Some("A1B2"))));
+        assert!(any_serial.matches(&usb_port(0x0403, 0x6001, None)));
+
+        let exact_serial =
+            UsbMatch { vid: 0x0403, pid: 0x6001, serial: Some("A1B2".to_owned()) };
+        assert!(exact_serial.matches(&usb_port(0x0403, 0x6001, Some("A1B2"))));
+        assert!(!exact_serial.matches(&usb_port(0x0403, 0x6001, Some("other"))));
+        assert!(!exact_serial.matches(&usb_port(0x0403, 0x6001, None)));
+    }
+}
@@ -0,0 +1,180 @@
+//! POSIX-specific plumbing for [`super::SerialTty`].
+use std::io::{Error, Result};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use super::{ClearBuffer, ControlLines};
+
+/// Put the freshly opened stream into non-blocking mode so the `mio` event
+/// loop can register it without stalling on reads/writes.
+pub(crate) fn set_nonblocking_serial(stream: &mio_serial::SerialStream) {
+    let fd = stream.as_raw_fd();
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+        if flags >= 0 {
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+    }
+}
+
+fn set_modem_bit(
+    stream: &mio_serial::SerialStream,
+    bit: libc::c_int,
+    on: bool,
+) -> Result<()> {
+    let fd = stream.as_raw_fd();
+    let request = if on { libc::TIOCMBIS } else { libc::TIOCMBIC };
+    let ret = unsafe { libc::ioctl(fd, request as _, &bit) };
+    if ret < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn set_dtr(stream: &mio_serial::SerialStream, on: bool) -> Result<()> {
+    set_modem_bit(stream, libc::TIOCM_DTR, on)
+}
+
+pub(crate) fn set_rts(stream: &mio_serial::SerialStream, on: bool) -> Result<()> {
+    set_modem_bit(stream, libc::TIOCM_RTS, on)
+}
+
+pub(crate) fn read_control_lines(
+    stream: &mio_serial::SerialStream,
+) -> Result<ControlLines> {
+    let fd = stream.as_raw_fd();
+    let mut bits: libc::c_int = 0;
+    let ret = unsafe { libc::ioctl(fd, libc::TIOCMGET as _, &mut bits) };
+    if ret < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(ControlLines {
+        cts: bits & libc::TIOCM_CTS != 0,
+        dsr: bits & libc::TIOCM_DSR != 0,
+        dcd: bits & libc::TIOCM_CAR != 0,
+        ri: bits & libc::TIOCM_RNG != 0,
+    })
+}
+
+/// Grab (or release) exclusive access to the device via `TIOCEXCL`/`TIOCNXCL`
+/// so that a second process opening the same path gets an error instead of
+/// silently sharing the port.
+pub(crate) fn set_exclusive(
+    stream: &mio_serial::SerialStream,
+    exclusive: bool,
+) -> Result<()> {
+    let fd = stream.as_raw_fd();
+    let request = if exclusive { libc::TIOCEXCL } else { libc::TIOCNXCL };
+    let ret = unsafe { libc::ioctl(fd, request as _) };
+    if ret < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Apply baud rate, framing and flow control from `config` to the already
+/// open stream in place, via a fresh `termios` and `tcsetattr`.
+pub(crate) fn reconfigure(
+    stream: &mio_serial::SerialStream,
+    config: &super::SerialTtyOptions,
+) -> Result<()> {
+    let fd = stream.as_raw_fd();
+    let mut term: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut term) } < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    unsafe {
+        libc::cfmakeraw(&mut term);
+        libc::cfsetspeed(&mut term, config.baud_rate as libc::speed_t);
+    }
+
+    term.c_cflag &= !libc::CSIZE;
+    term.c_cflag |= match config.data_bits {
+        mio_serial::DataBits::Five => libc::CS5,
+        mio_serial::DataBits::Six => libc::CS6,
+        mio_serial::DataBits::Seven => libc::CS7,
+        mio_serial::DataBits::Eight => libc::CS8,
+    };
+
+    match config.parity {
+        mio_serial::Parity::None => term.c_cflag &= !(libc::PARENB | libc::PARODD),
+        mio_serial::Parity::Odd => term.c_cflag |= libc::PARENB | libc::PARODD,
+        mio_serial::Parity::Even => {
+            term.c_cflag |= libc::PARENB;
+            term.c_cflag &= !libc::PARODD;
+        },
+    }
+
+    match config.stop_bits {
+        mio_serial::StopBits::One => term.c_cflag &= !libc::CSTOPB,
+        mio_serial::StopBits::Two => term.c_cflag |= libc::CSTOPB,
+    }
+
+    match config.flow_control {
+        mio_serial::FlowControl::None => {
+            term.c_cflag &= !libc::CRTSCTS;
+            term.c_iflag &= !(libc::IXON | libc::IXOFF);
+        },
+        mio_serial::FlowControl::Software => {
+            term.c_cflag &= !libc::CRTSCTS;
+            term.c_iflag |= libc::IXON | libc::IXOFF;
+        },
+        mio_serial::FlowControl::Hardware => {
+            term.c_cflag |= libc::CRTSCTS;
+            term.c_iflag &= !(libc::IXON | libc::IXOFF);
+        },
+    }
+
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &term) } < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Assert a transmit BREAK condition for `duration` via `TIOCSBRK`/`TIOCCBRK`,
+/// e.g. to interrupt a Linux serial console into SysRq or a bootloader.
+pub(crate) fn send_break(
+    stream: &mio_serial::SerialStream,
+    duration: Duration,
+) -> Result<()> {
+    send_break_raw(stream.as_raw_fd(), duration)
+}
+
+/// Same as [`send_break`], operating on a bare fd so it can be called from a
+/// thread that doesn't hold the `SerialStream` itself.
+pub(crate) fn send_break_raw(fd: std::os::unix::io::RawFd, duration: Duration) -> Result<()> {
+    if unsafe { libc::ioctl(fd, libc::TIOCSBRK as _) } < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    std::thread::sleep(duration);
+
+    if unsafe { libc::ioctl(fd, libc::TIOCCBRK as _) } < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+pub(crate) fn clear(
+    stream: &mio_serial::SerialStream,
+    buffer_to_clear: ClearBuffer,
+) -> Result<()> {
+    let fd = stream.as_raw_fd();
+    let queue_selector = match buffer_to_clear {
+        ClearBuffer::Input => libc::TCIFLUSH,
+        ClearBuffer::Output => libc::TCOFLUSH,
+        ClearBuffer::All => libc::TCIOFLUSH,
+    };
+    let ret = unsafe { libc::tcflush(fd, queue_selector) };
+    if ret < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Windows-specific plumbing for [`super::SerialTty`].
+use std::io::{Error, Result};
+use std::os::windows::io::AsRawHandle;
+use std::time::Duration;
+
+use windows_sys::Win32::Devices::Communication::{
+    ClearCommBreak, EscapeCommFunction, GetCommModemStatus, GetCommState, PurgeComm,
+    SetCommBreak, SetCommState, CLRDTR, CLRRTS, DCB, EVENPARITY, MS_CTS_ON, MS_DSR_ON,
+    MS_RING_ON, MS_RLSD_ON, NOPARITY, ODDPARITY, ONESTOPBIT, PURGE_RXCLEAR,
+    PURGE_TXCLEAR, SETDTR, SETRTS, TWOSTOPBITS,
+};
+use windows_sys::Win32::Foundation::HANDLE;
+
+use super::{ClearBuffer, ControlLines, SerialTtyOptions};
+
+pub(crate) fn open(
+    config: &SerialTtyOptions,
+) -> mio_serial::Result<mio_serial::SerialStream> {
+    mio_serial::SerialStream::open(&config.in_to_builder())
+}
+
+fn escape_comm_function(
+    stream: &mio_serial::SerialStream,
+    function: u32,
+) -> Result<()> {
+    let handle = stream.as_raw_handle() as HANDLE;
+    let ok = unsafe { EscapeCommFunction(handle, function) };
+    if ok == 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn set_dtr(stream: &mio_serial::SerialStream, on: bool) -> Result<()> {
+    escape_comm_function(stream, if on { SETDTR } else { CLRDTR })
+}
+
+pub(crate) fn set_rts(stream: &mio_serial::SerialStream, on: bool) -> Result<()> {
+    escape_comm_function(stream, if on { SETRTS } else { CLRRTS })
+}
+
+pub(crate) fn read_control_lines(
+    stream: &mio_serial::SerialStream,
+) -> Result<ControlLines> {
+    let handle = stream.as_raw_handle() as HANDLE;
+    let mut status: u32 = 0;
+    let ok = unsafe { GetCommModemStatus(handle, &mut status) };
+    if ok == 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(ControlLines {
+        cts: status & MS_CTS_ON != 0,
+        dsr: status & MS_DSR_ON != 0,
+        dcd: status & MS_RLSD_ON != 0,
+        ri: status & MS_RING_ON != 0,
+    })
+}
+
+/// Apply baud rate, framing and flow control from `config` to the already
+/// open handle in place, via a fresh `DCB` and `SetCommState`.
+pub(crate) fn reconfigure(
+    stream: &mio_serial::SerialStream,
+    config: &SerialTtyOptions,
+) -> Result<()> {
+    let handle = stream.as_raw_handle() as HANDLE;
+    let mut dcb: DCB = unsafe { std::mem::zeroed() };
+    dcb.DCBlength = std::mem::size_of::<DCB>() as u32;
+
+    if unsafe { GetCommState(handle, &mut dcb) } == 0 {
+        return Err(Error::last_os_error());
+    }
+
+    dcb.BaudRate = config.baud_rate;
+    dcb.ByteSize = match config.data_bits {
+        mio_serial::DataBits::Five => 5,
+        mio_serial::DataBits::Six => 6,
+        mio_serial::DataBits::Seven => 7,
+        mio_serial::DataBits::Eight => 8,
+    };
+    dcb.Parity = match config.parity {
+        mio_serial::Parity::None => NOPARITY as u8,
+        mio_serial::Parity::Odd => ODDPARITY as u8,
+        mio_serial::Parity::Even => EVENPARITY as u8,
+    };
+    dcb.StopBits = match config.stop_bits {
+        mio_serial::StopBits::One => ONESTOPBIT as u8,
+        mio_serial::StopBits::Two => TWOSTOPBITS as u8,
+    };
+    dcb.set_fOutxCtsFlow(
+        (config.flow_control == mio_serial::FlowControl::Hardware) as u32,
+    );
+    dcb.set_fInX((config.flow_control == mio_serial::FlowControl::Software) as u32);
+    dcb.set_fOutX((config.flow_control == mio_serial::FlowControl::Software) as u32);
+
+    if unsafe { SetCommState(handle, &dcb) } == 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Assert a transmit BREAK condition for `duration` via
+/// `SetCommBreak`/`ClearCommBreak`.
+pub(crate) fn send_break(
+    stream: &mio_serial::SerialStream,
+    duration: Duration,
+) -> Result<()> {
+    send_break_raw(stream.as_raw_handle(), duration)
+}
+
+/// Same as [`send_break`], operating on a bare handle so it can be called
+/// from a thread that doesn't hold the `SerialStream` itself.
+pub(crate) fn send_break_raw(
+    raw_handle: std::os::windows::io::RawHandle,
+    duration: Duration,
+) -> Result<()> {
+    let handle = raw_handle as HANDLE;
+    if unsafe { SetCommBreak(handle) } == 0 {
+        return Err(Error::last_os_error());
+    }
+
+    std::thread::sleep(duration);
+
+    if unsafe { ClearCommBreak(handle) } == 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+pub(crate) fn clear(
+    stream: &mio_serial::SerialStream,
+    buffer_to_clear: ClearBuffer,
+) -> Result<()> {
+    let handle = stream.as_raw_handle() as HANDLE;
+    let flags = match buffer_to_clear {
+        ClearBuffer::Input => PURGE_RXCLEAR,
+        ClearBuffer::Output => PURGE_TXCLEAR,
+        ClearBuffer::All => PURGE_RXCLEAR | PURGE_TXCLEAR,
+    };
+    let ok = unsafe { PurgeComm(handle, flags) };
+    if ok == 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}